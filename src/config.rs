@@ -1,17 +1,18 @@
-use std::path::{Path, PathBuf};
+use crate::globset::GlobSet;
+use crate::print_error;
 use clap::Parser;
 use regex::Regex;
+use std::path::{Path, PathBuf};
 use std::process::exit;
-use crate::print_error;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum FindType {
     File,
     Dir,
     Symlink,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub enum SizeType {
     Eq(u64),
     Gte(u64),
@@ -46,13 +47,50 @@ impl SizeType {
     }
 }
 
+/// How a matched entry's name is checked: a single compiled regex (from
+/// `--regex`/`--iname`), or a [`GlobSet`] built from one or more
+/// `--name`/`--glob` patterns.
+#[derive(Clone, Debug)]
+pub enum NameMatcher {
+    Regex(Regex),
+    Glob(GlobSet),
+}
+
+impl NameMatcher {
+    pub fn is_match(&self, name: &str) -> bool {
+        match self {
+            NameMatcher::Regex(regex) => regex.is_match(name),
+            NameMatcher::Glob(glob_set) => glob_set.is_match(name),
+        }
+    }
+}
+
+/// What to do with a match that isn't just printed: run a command for it
+/// right away (`--exec`), or collect matches and run one command against the
+/// whole batch once traversal finishes (`--exec-batch`). Either way the
+/// `Vec<String>` is the command and its arguments; placeholder tokens are
+/// expanded per match for `--exec` only, while `--exec-batch` appends the
+/// matches as trailing arguments.
+#[derive(Clone, Debug)]
+pub enum ExecMode {
+    PerMatch(Vec<String>),
+    Batch(Vec<String>),
+}
+
 #[derive(Debug)]
 pub struct Config {
     pub dir: PathBuf,
     pub find_type: FindType,
     pub size_in_bytes: Option<SizeType>,
-    pub regex: Option<Regex>,
+    pub name_matcher: Option<NameMatcher>,
     pub depth: Option<u16>,
+    pub threads: Option<usize>,
+    pub hidden: bool,
+    pub no_ignore: bool,
+    pub exec: Option<ExecMode>,
+    pub absolute_path: bool,
+    pub follow: bool,
+    pub path_separator: String,
 }
 
 impl Default for Config {
@@ -61,8 +99,15 @@ impl Default for Config {
             dir: Path::new(".").to_owned(),
             find_type: FindType::File,
             size_in_bytes: None,
-            regex: None,
+            name_matcher: None,
             depth: None,
+            threads: None,
+            hidden: false,
+            no_ignore: false,
+            exec: None,
+            absolute_path: false,
+            follow: false,
+            path_separator: std::path::MAIN_SEPARATOR.to_string(),
         }
     }
 }
@@ -74,20 +119,17 @@ enum NameMatcherError {
 
 fn create_name_matcher(
     regex: Option<String>,
-    name: Option<String>,
+    name: Vec<String>,
     iname: Option<String>,
-) -> Result<Regex, NameMatcherError> {
-    match (regex, name, iname) {
-        (Some(regex), None, None) => Ok(Regex::new(&regex).unwrap()),
-        (None, Some(name), None) => {
-            let fixed_name = name.replace("*", "(.*)");
-            Ok(Regex::new(&format!("^{}$", fixed_name)).unwrap())
-        }
-        (None, None, Some(iname)) => {
+) -> Result<NameMatcher, NameMatcherError> {
+    match (regex, name.is_empty(), iname) {
+        (Some(regex), true, None) => Ok(NameMatcher::Regex(Regex::new(&regex).unwrap())),
+        (None, false, None) => Ok(NameMatcher::Glob(GlobSet::new(&name))),
+        (None, true, Some(iname)) => {
             let fixed_name = iname.replace("*", "(.*)");
-            Ok(Regex::new(&format!("(?i)^{}$", fixed_name)).unwrap())
+            Ok(NameMatcher::Regex(Regex::new(&format!("(?i)^{}$", fixed_name)).unwrap()))
         }
-        (None, None, None) => unreachable!(),
+        (None, true, None) => unreachable!(),
         _ => Err(NameMatcherError::MoreThanOneMatcher),
     }
 }
@@ -117,7 +159,7 @@ impl Config {
         }
 
         if raw_config.regex.is_some() ||
-            raw_config.name.is_some() ||
+            !raw_config.name.is_empty() ||
             raw_config.iname.is_some() {
             let matcher_result =
                 create_name_matcher(raw_config.regex, raw_config.name, raw_config.iname);
@@ -125,7 +167,7 @@ impl Config {
                 print_error!("name matcher can't be decoded: {:?}", matcher_result.unwrap_err());
                 exit(1);
             }
-            config.regex = Some(matcher_result.unwrap());
+            config.name_matcher = Some(matcher_result.unwrap());
         }
         if let Some(depth) = raw_config.depth {
             if depth == 0 {
@@ -134,6 +176,31 @@ impl Config {
             }
             config.depth = Some(depth);
         }
+        if let Some(threads) = raw_config.threads {
+            if threads == 0 {
+                print_error!("threads should be >0");
+                exit(1);
+            }
+            config.threads = Some(threads);
+        }
+
+        config.hidden = raw_config.hidden || raw_config.unrestricted;
+        config.no_ignore = raw_config.no_ignore || raw_config.unrestricted;
+        config.absolute_path = raw_config.absolute_path;
+        config.follow = raw_config.follow;
+        if let Some(path_separator) = raw_config.path_separator {
+            config.path_separator = path_separator;
+        }
+
+        match (raw_config.exec, raw_config.exec_batch) {
+            (Some(cmd), None) => config.exec = Some(ExecMode::PerMatch(cmd)),
+            (None, Some(cmd)) => config.exec = Some(ExecMode::Batch(cmd)),
+            (None, None) => {}
+            (Some(_), Some(_)) => {
+                print_error!("--exec and --exec-batch can't be used together");
+                exit(1);
+            }
+        }
 
         config
     }
@@ -146,12 +213,30 @@ struct RawConfig {
     find_type: String,
     #[arg(long, allow_hyphen_values = true)]
     size: Option<String>,
-    #[arg(long)]
-    name: Option<String>,
+    #[arg(long, visible_alias = "glob")]
+    name: Vec<String>,
     #[arg(long)]
     iname: Option<String>,
     #[arg(long)]
     regex: Option<String>,
     #[arg(long)]
     depth: Option<u16>,
+    #[arg(short = 'j', long)]
+    threads: Option<usize>,
+    #[arg(short = 'H', long)]
+    hidden: bool,
+    #[arg(short = 'I', long = "no-ignore")]
+    no_ignore: bool,
+    #[arg(short = 'u')]
+    unrestricted: bool,
+    #[arg(long, num_args = 1.., allow_hyphen_values = true, value_terminator = ";")]
+    exec: Option<Vec<String>>,
+    #[arg(long, num_args = 1.., allow_hyphen_values = true, value_terminator = ";")]
+    exec_batch: Option<Vec<String>>,
+    #[arg(short = 'a', long)]
+    absolute_path: bool,
+    #[arg(short = 'L', long)]
+    follow: bool,
+    #[arg(long)]
+    path_separator: Option<String>,
 }
@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Expands the placeholder tokens fd recognizes in an `--exec`/`--exec-batch`
+/// template against a matched path: `{}` is `rendered` (the path as it was
+/// printed, i.e. with `--path-separator` and any directory trailing slash
+/// already applied), `{/}` the basename, `{//}` the parent directory, `{.}`
+/// the path with its extension stripped, and `{/.}` the basename with its
+/// extension stripped. The latter four are derived from `path` itself rather
+/// than `rendered`, since they pull apart filesystem structure that a custom
+/// separator doesn't change.
+fn expand_template(template: &str, path: &Path, rendered: &str) -> String {
+    let basename = path.file_name().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let parent = path.parent().map(|p| p.display().to_string()).unwrap_or_default();
+    let stripped = strip_extension(path);
+    let basename_stripped = strip_extension(Path::new(&basename));
+
+    template
+        .replace("{//}", &parent)
+        .replace("{/.}", &basename_stripped.to_string_lossy())
+        .replace("{.}", &stripped.to_string_lossy())
+        .replace("{/}", &basename)
+        .replace("{}", rendered)
+}
+
+fn strip_extension(path: &Path) -> PathBuf {
+    match path.extension() {
+        None => path.to_path_buf(),
+        Some(ext) => {
+            let full = path.to_string_lossy();
+            let suffix = format!(".{}", ext.to_string_lossy());
+            match full.strip_suffix(suffix.as_str()) {
+                Some(stripped) => PathBuf::from(stripped),
+                None => path.to_path_buf(),
+            }
+        }
+    }
+}
+
+fn expand_all(template: &[String], path: &Path, rendered: &str) -> Vec<String> {
+    template.iter().map(|arg| expand_template(arg, path, rendered)).collect()
+}
+
+fn run_command(argv: &[String]) -> i32 {
+    let Some((cmd, args)) = argv.split_first() else {
+        return 0;
+    };
+    match Command::new(cmd).args(args).status() {
+        Ok(status) => status.code().unwrap_or(1),
+        Err(e) => {
+            crate::print_error!("{}: {}", cmd, e);
+            1
+        }
+    }
+}
+
+/// Runs `template` once per match, with placeholders expanded against
+/// `path`/`rendered`. Called from the worker thread that found the match.
+pub fn run_per_match(template: &[String], path: &Path, rendered: &str) -> i32 {
+    run_command(&expand_all(template, path, rendered))
+}
+
+/// Runs `template` once for the whole set of matches, with every match's
+/// already-rendered display form appended as a trailing argument
+/// (placeholders aren't expanded here, since there's no single path to
+/// expand them against).
+pub fn run_batch(template: &[String], paths: &[String]) -> i32 {
+    if paths.is_empty() {
+        return 0;
+    }
+
+    let mut argv = template.to_vec();
+    argv.extend(paths.iter().cloned());
+
+    run_command(&argv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_template_placeholders() {
+        let path = Path::new("/tmp/foo/bar.txt");
+        let rendered = "/tmp/foo/bar.txt";
+        assert_eq!(expand_template("{}", path, rendered), "/tmp/foo/bar.txt");
+        assert_eq!(expand_template("{/}", path, rendered), "bar.txt");
+        assert_eq!(expand_template("{//}", path, rendered), "/tmp/foo");
+        assert_eq!(expand_template("{.}", path, rendered), "/tmp/foo/bar");
+        assert_eq!(expand_template("{/.}", path, rendered), "bar");
+    }
+
+    #[test]
+    fn test_expand_template_uses_rendered_for_full_path_placeholder() {
+        let path = Path::new("/tmp/foo");
+        assert_eq!(expand_template("{}", path, "/tmp/foo/"), "/tmp/foo/");
+    }
+
+    #[test]
+    fn test_expand_all_keeps_fixed_args() {
+        let path = Path::new("/tmp/bar.txt");
+        let template = vec!["mv".to_string(), "{}".to_string(), "/backup/".to_string()];
+        assert_eq!(
+            expand_all(&template, path, "/tmp/bar.txt"),
+            vec!["mv".to_string(), "/tmp/bar.txt".to_string(), "/backup/".to_string()]
+        );
+    }
+}
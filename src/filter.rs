@@ -0,0 +1,107 @@
+use crate::config::{Config, FindType, NameMatcher, SizeType};
+use crate::PathType;
+use std::fs;
+use std::path::Path;
+
+/// A filesystem entry paired with the metadata fetched for it, so every
+/// filter that needs it can share a single `stat` call instead of each
+/// re-querying the filesystem. The metadata is `symlink_metadata` normally,
+/// or `metadata` (i.e. symlinks resolved) when `--follow` is in effect.
+pub struct DirEntry<'p> {
+    path: &'p Path,
+    metadata: Option<fs::Metadata>,
+}
+
+impl<'p> DirEntry<'p> {
+    pub fn new(path: &'p Path, follow: bool) -> Self {
+        let metadata = if follow { fs::metadata(path) } else { fs::symlink_metadata(path) };
+        Self { path, metadata: metadata.ok() }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path
+    }
+
+    pub fn metadata(&self) -> Option<&fs::Metadata> {
+        self.metadata.as_ref()
+    }
+
+    pub fn path_type(&self) -> PathType {
+        match &self.metadata {
+            Some(meta) if meta.file_type().is_dir() => PathType::Directory,
+            Some(meta) if meta.file_type().is_symlink() => PathType::Symlink,
+            Some(meta) if meta.file_type().is_file() => PathType::File,
+            _ => PathType::Unknown,
+        }
+    }
+
+    pub fn len(&self) -> Option<u64> {
+        self.metadata.as_ref().map(|m| m.len())
+    }
+}
+
+/// A single match predicate over a [`DirEntry`]. `Config` is compiled into a
+/// `Vec<Box<dyn Filter>>` once at startup, so the traversal code stays
+/// ignorant of what's actually being matched: it just runs the whole list.
+pub trait Filter: Send + Sync {
+    fn matches(&self, entry: &DirEntry) -> bool;
+}
+
+pub struct NameFilter(pub NameMatcher);
+
+impl Filter for NameFilter {
+    fn matches(&self, entry: &DirEntry) -> bool {
+        let maybe_file_name = entry.path().file_name().and_then(|os_str| os_str.to_str());
+        match maybe_file_name {
+            None => false,
+            Some(file_name) => self.0.is_match(file_name),
+        }
+    }
+}
+
+pub struct SizeFilter(pub SizeType);
+
+impl Filter for SizeFilter {
+    fn matches(&self, entry: &DirEntry) -> bool {
+        let size = match entry.len() {
+            Some(size) => size,
+            None => return false,
+        };
+        match self.0 {
+            SizeType::Gte(s) => size >= s,
+            SizeType::Eq(s) => size == s,
+            SizeType::Le(s) => size <= s,
+        }
+    }
+}
+
+pub struct TypeFilter(pub FindType);
+
+impl Filter for TypeFilter {
+    fn matches(&self, entry: &DirEntry) -> bool {
+        matches!(
+            (self.0, entry.path_type()),
+            (FindType::File, PathType::File)
+                | (FindType::Dir, PathType::Directory)
+                | (FindType::Symlink, PathType::Symlink)
+        )
+    }
+}
+
+/// Builds the filter chain a traversal will apply to every entry, from the
+/// predicates the user actually asked for.
+pub fn build_filters(config: &Config) -> Vec<Box<dyn Filter>> {
+    let mut filters: Vec<Box<dyn Filter>> = Vec::new();
+
+    filters.push(Box::new(TypeFilter(config.find_type)));
+
+    if let Some(name_matcher) = &config.name_matcher {
+        filters.push(Box::new(NameFilter(name_matcher.clone())));
+    }
+
+    if let Some(size) = &config.size_in_bytes {
+        filters.push(Box::new(SizeFilter(size.clone())));
+    }
+
+    filters
+}
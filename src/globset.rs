@@ -0,0 +1,141 @@
+use regex::RegexSet;
+use std::collections::HashSet;
+
+/// A name matcher built from many glob patterns at once.
+///
+/// Compiling every `--name`/`--glob` pattern into one combined regex gets
+/// slow as the pattern list grows, and most real-world patterns don't need a
+/// regex engine at all. Each pattern is classified up front into the
+/// cheapest bucket that can decide it: a bare literal (`Makefile`) goes into
+/// an exact `HashSet` lookup, a single leading `*` (`*.rs`) becomes a suffix
+/// check, a single trailing `*` (`foo*`) becomes a prefix check, and
+/// anything with richer glob syntax (`a?b[0-9]*`) falls back to a combined
+/// `RegexSet`. Matching tries the cheap buckets first and only touches the
+/// `RegexSet` for the residual patterns.
+#[derive(Clone, Debug)]
+pub struct GlobSet {
+    exact: HashSet<String>,
+    suffixes: HashSet<String>,
+    prefixes: HashSet<String>,
+    regex_set: Option<RegexSet>,
+}
+
+enum Bucket {
+    Exact,
+    Suffix(String),
+    Prefix(String),
+    Regex,
+}
+
+fn is_meta(c: char) -> bool {
+    matches!(c, '*' | '?' | '[' | ']')
+}
+
+fn classify(pattern: &str) -> Bucket {
+    if !pattern.contains(is_meta) {
+        return Bucket::Exact;
+    }
+    if let Some(tail) = pattern.strip_prefix('*')
+        && !tail.contains(is_meta)
+    {
+        return Bucket::Suffix(tail.to_owned());
+    }
+    if let Some(head) = pattern.strip_suffix('*')
+        && !head.contains(is_meta)
+    {
+        return Bucket::Prefix(head.to_owned());
+    }
+    Bucket::Regex
+}
+
+/// Translates a `--name`/`--glob` pattern into an anchored regex, honoring
+/// `*` (any run of characters), `?` (exactly one character), `[...]`
+/// (passed through verbatim, since it's already valid regex syntax), and
+/// escaping the other regex metacharacters glob patterns don't use (`.`
+/// chief among them, or `a.rs` would match `arXrs`).
+fn glob_to_regex(pattern: &str) -> String {
+    let mut out = String::from("^");
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => out.push_str(".*"),
+            '?' => out.push_str("[^/]"),
+            '.' => out.push_str("\\."),
+            '[' => {
+                out.push('[');
+                while let Some(&next) = chars.peek() {
+                    chars.next();
+                    out.push(next);
+                    if next == ']' {
+                        break;
+                    }
+                }
+            }
+            other => out.push_str(&regex::escape(other.encode_utf8(&mut [0; 4]))),
+        }
+    }
+
+    out.push('$');
+    out
+}
+
+impl GlobSet {
+    pub fn new(patterns: &[String]) -> Self {
+        let mut exact = HashSet::new();
+        let mut suffixes = HashSet::new();
+        let mut prefixes = HashSet::new();
+        let mut residual = Vec::new();
+
+        for pattern in patterns {
+            match classify(pattern) {
+                Bucket::Exact => {
+                    exact.insert(pattern.clone());
+                }
+                Bucket::Suffix(tail) => {
+                    suffixes.insert(tail);
+                }
+                Bucket::Prefix(head) => {
+                    prefixes.insert(head);
+                }
+                Bucket::Regex => residual.push(glob_to_regex(pattern)),
+            }
+        }
+
+        let regex_set = if residual.is_empty() {
+            None
+        } else {
+            Some(RegexSet::new(&residual).expect("glob patterns compile to valid regexes"))
+        };
+
+        Self { exact, suffixes, prefixes, regex_set }
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        self.exact.contains(name)
+            || self.suffixes.iter().any(|tail| name.ends_with(tail.as_str()))
+            || self.prefixes.iter().any(|head| name.starts_with(head.as_str()))
+            || self.regex_set.as_ref().is_some_and(|set| set.is_match(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_question_mark_matches_exactly_one_char() {
+        let set = GlobSet::new(&["fil?.rs".to_string()]);
+        assert_eq!(set.is_match("file.rs"), true);
+        assert_eq!(set.is_match("filx.rs"), true);
+        assert_eq!(set.is_match("fil.rs"), false);
+        assert_eq!(set.is_match("file1.rs"), false);
+    }
+
+    #[test]
+    fn test_dot_is_not_a_wildcard() {
+        let set = GlobSet::new(&["a.rs".to_string(), "*.rs".to_string()]);
+        assert_eq!(set.is_match("arXrs"), false);
+        assert_eq!(set.is_match("a.rs"), true);
+    }
+}
@@ -0,0 +1,173 @@
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// One negation-aware rule parsed from a line of a `.gitignore`/`.ignore`
+/// file.
+struct Rule {
+    regex: Regex,
+    negate: bool,
+    dir_only: bool,
+    /// Whether the pattern is rooted to the directory holding the ignore
+    /// file (it contained a `/` other than a trailing one) or may match at
+    /// any depth below it (a bare basename pattern).
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Rule> {
+        let raw = line.trim_end();
+        if raw.is_empty() || raw.starts_with('#') {
+            return None;
+        }
+
+        let (raw, negate) = match raw.strip_prefix('!') {
+            Some(rest) => (rest, true),
+            None => (raw, false),
+        };
+
+        let dir_only = raw.ends_with('/');
+        let raw = raw.strip_suffix('/').unwrap_or(raw);
+        let anchored = raw.contains('/');
+        let pattern = raw.strip_prefix('/').unwrap_or(raw);
+
+        Regex::new(&glob_to_regex(pattern))
+            .ok()
+            .map(|regex| Rule { regex, negate, dir_only, anchored })
+    }
+}
+
+/// Translates a gitignore-style glob (`*`, `**`, `?`) into an anchored regex.
+fn glob_to_regex(pattern: &str) -> String {
+    let mut out = String::from("^");
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                out.push_str(".*");
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                }
+            }
+            '*' => out.push_str("[^/]*"),
+            '?' => out.push_str("[^/]"),
+            '.' => out.push_str("\\."),
+            other => out.push(other),
+        }
+    }
+
+    out.push('$');
+    out
+}
+
+/// The compiled rules from a single directory's `.gitignore`/`.ignore`.
+struct IgnoreFile {
+    rules: Vec<Rule>,
+}
+
+impl IgnoreFile {
+    fn load(dir: &Path) -> Option<IgnoreFile> {
+        let mut rules = Vec::new();
+        for name in [".gitignore", ".ignore"] {
+            if let Ok(contents) = fs::read_to_string(dir.join(name)) {
+                rules.extend(contents.lines().filter_map(Rule::parse));
+            }
+        }
+        if rules.is_empty() {
+            None
+        } else {
+            Some(IgnoreFile { rules })
+        }
+    }
+
+    /// `relative` is `/`-separated and relative to this file's directory.
+    /// Returns `None` when nothing in the file says anything about the
+    /// path; `Some(true)`/`Some(false)` when the last matching rule
+    /// ignores/un-ignores it (later rules in the file win, mirroring git).
+    fn is_match(&self, relative: &str, is_dir: bool) -> Option<bool> {
+        let basename = relative.rsplit('/').next().unwrap_or(relative);
+        let mut result = None;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let subject = if rule.anchored { relative } else { basename };
+            if rule.regex.is_match(subject) {
+                result = Some(!rule.negate);
+            }
+        }
+        result
+    }
+}
+
+/// The ignore files collected along the path from the traversal root down to
+/// the current directory. Each traversal job carries its own stack (cheap to
+/// clone — it's just `Arc`s) since jobs hop between worker threads instead
+/// of sharing a single call stack.
+#[derive(Clone, Default)]
+pub struct IgnoreStack {
+    layers: Vec<(PathBuf, Arc<IgnoreFile>)>,
+}
+
+impl IgnoreStack {
+    /// Returns a new stack with `dir`'s own ignore file (if any) layered on
+    /// top, to use while inspecting `dir`'s children.
+    pub fn push(&self, dir: &Path) -> IgnoreStack {
+        let mut layers = self.layers.clone();
+        if let Some(file) = IgnoreFile::load(dir) {
+            layers.push((dir.to_path_buf(), Arc::new(file)));
+        }
+        IgnoreStack { layers }
+    }
+
+    /// Whether `path` should be skipped, consulting shallower layers first
+    /// and letting deeper ones override them.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut ignored = false;
+        for (root, file) in &self.layers {
+            let Ok(relative) = path.strip_prefix(root) else { continue };
+            let relative = relative.to_string_lossy().replace(std::path::MAIN_SEPARATOR, "/");
+            if let Some(result) = file.is_match(&relative, is_dir) {
+                ignored = result;
+            }
+        }
+        ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_to_regex_wildcards() {
+        let re = Regex::new(&glob_to_regex("*.log")).unwrap();
+        assert_eq!(re.is_match("build.log"), true);
+        assert_eq!(re.is_match("build.logx"), false);
+
+        let re = Regex::new(&glob_to_regex("a?c")).unwrap();
+        assert_eq!(re.is_match("abc"), true);
+        assert_eq!(re.is_match("ac"), false);
+
+        let re = Regex::new(&glob_to_regex("**/target")).unwrap();
+        assert_eq!(re.is_match("foo/bar/target"), true);
+    }
+
+    #[test]
+    fn test_negation_and_dir_only() {
+        let dir = std::env::temp_dir().join("rfind-ignore-test-negation-and-dir-only");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(".gitignore"), "*.log\n!keep.log\nbuild/\n").unwrap();
+
+        let file = IgnoreFile::load(&dir).unwrap();
+        assert_eq!(file.is_match("debug.log", false), Some(true));
+        assert_eq!(file.is_match("keep.log", false), Some(false));
+        assert_eq!(file.is_match("build", true), Some(true));
+        assert_eq!(file.is_match("build", false), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,12 +1,25 @@
 #![feature(let_chains)]
 
 mod config;
+mod exec;
+mod filter;
+mod globset;
+mod ignore;
 
-use crate::config::{FindType, SizeType};
-use config::Config;
+use config::{Config, ExecMode};
+use filter::{build_filters, DirEntry, Filter};
+use ignore::IgnoreStack;
+use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
+use std::io::{self, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
 use std::process::exit;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
 #[macro_export]
 macro_rules! print_error {
@@ -16,110 +29,313 @@ macro_rules! print_error {
 }
 
 #[derive(Eq, PartialEq, Debug)]
-enum PathType {
+pub(crate) enum PathType {
     Directory,
     File,
     Symlink,
     Unknown,
 }
 
-fn get_type(path: &Path) -> PathType {
-    match fs::symlink_metadata(path) {
-        Ok(meta) if meta.file_type().is_dir() => PathType::Directory,
-        Ok(meta) if meta.file_type().is_symlink() => PathType::Symlink,
-        Ok(meta) if meta.file_type().is_file() => PathType::File,
-        _ => PathType::Unknown,
+/// A unit of traversal work: a directory (or the root path) to inspect at a
+/// known depth. Depth travels with the job instead of living on `Find`
+/// because jobs now hop between worker threads.
+struct Job {
+    path: PathBuf,
+    depth: u16,
+    ignore_stack: IgnoreStack,
+}
+
+/// Whether `name` is a dotfile, i.e. hidden by default.
+fn is_hidden(name: &std::ffi::OsStr) -> bool {
+    name.to_str().is_some_and(|s| s.starts_with('.'))
+}
+
+/// Resolves `path` to an absolute form by joining it onto the current
+/// directory if needed and lexically collapsing `.`/`..`, without touching
+/// the filesystem (so it works even for paths `fs::canonicalize` would
+/// reject, e.g. broken symlinks).
+fn make_absolute(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
     }
+    normalized
+}
+
+/// Number of worker threads to use for traversal, defaulting to the
+/// available parallelism of the host.
+fn worker_count(config: &Config) -> usize {
+    config.threads.unwrap_or_else(|| {
+        thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+    })
+}
+
+/// State shared by every worker thread: the job queue, the outstanding-job
+/// counter that drives shutdown, the single stdout writer, and whatever
+/// `--exec`/`--exec-batch` needs to track across matches.
+struct Shared {
+    job_tx: Sender<Job>,
+    pending: AtomicUsize,
+    done: AtomicBool,
+    writer: Mutex<io::Stdout>,
+    batch_matches: Mutex<Vec<String>>,
+    /// The exit code of the first failing `--exec` match, if any (0 means
+    /// none yet), so rfind's own exit code reflects what the child actually
+    /// returned instead of collapsing every failure to `1`.
+    exec_failed: AtomicI32,
+    /// `(dev, ino)` pairs of directories already descended into, so
+    /// `--follow` doesn't chase a symlink cycle forever.
+    visited: Mutex<HashSet<(u64, u64)>>,
 }
 
 struct Find {
     config: Config,
-    depth: u16,
+    filters: Vec<Box<dyn Filter>>,
 }
 
 impl Find {
     fn new(config: Config) -> Self {
-        Self { config, depth: 0 }
-    }
-
-    fn match_name(&self, path: &Path) -> bool {
-        match &self.config.regex {
-            None => true,
-            Some(matcher) => {
-                let maybe_file_name =
-                    path.file_name().and_then(|os_str| os_str.to_str());
-                match maybe_file_name {
-                    None => false,
-                    Some(file_name) => matcher.is_match(file_name),
-                }
+        let filters = build_filters(&config);
+        Self { config, filters }
+    }
+
+    fn matches(&self, entry: &DirEntry) -> bool {
+        self.filters.iter().all(|filter| filter.matches(entry))
+    }
+
+    /// Walks `root` using a pool of worker threads that pull directory jobs
+    /// off a shared queue and push newly discovered subdirectories back onto
+    /// it, so large trees spread their I/O across all available cores.
+    /// Exits the process with the exit code of any `--exec`/`--exec-batch`
+    /// command that failed.
+    fn run(self, root: PathBuf) {
+        let num_threads = worker_count(&self.config);
+        let (job_tx, job_rx) = mpsc::channel::<Job>();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let shared = Arc::new(Shared {
+            job_tx,
+            // One outstanding job (the root) to start with; workers bump
+            // this up for every subdirectory they discover and down for
+            // every job they finish, so it hits zero exactly when there is
+            // no work left.
+            pending: AtomicUsize::new(1),
+            done: AtomicBool::new(false),
+            writer: Mutex::new(io::stdout()),
+            batch_matches: Mutex::new(Vec::new()),
+            exec_failed: AtomicI32::new(0),
+            visited: Mutex::new(HashSet::new()),
+        });
+        let find = Arc::new(self);
+
+        shared
+            .job_tx
+            .send(Job { path: root, depth: 0, ignore_stack: IgnoreStack::default() })
+            .expect("root job send");
+
+        let handles: Vec<_> = (0..num_threads)
+            .map(|_| {
+                let job_rx = Arc::clone(&job_rx);
+                let shared = Arc::clone(&shared);
+                let find = Arc::clone(&find);
+                thread::spawn(move || worker_loop(find, job_rx, shared))
+            })
+            .collect();
+
+        for handle in handles {
+            let _ = handle.join();
+        }
+
+        let mut exit_code = shared.exec_failed.load(Ordering::Acquire);
+
+        if let Some(ExecMode::Batch(template)) = &find.config.exec {
+            let matches = std::mem::take(&mut *shared.batch_matches.lock().unwrap());
+            let code = exec::run_batch(template, &matches);
+            if code != 0 {
+                exit_code = code;
             }
         }
+
+        exit(exit_code);
     }
+}
+
+fn worker_loop(find: Arc<Find>, job_rx: Arc<Mutex<mpsc::Receiver<Job>>>, shared: Arc<Shared>) {
+    loop {
+        if shared.done.load(Ordering::Acquire) {
+            return;
+        }
 
-    fn match_size(&self, path: &Path) -> bool {
-        let size = match path.metadata() {
-            Ok(m) => m.len(),
-            Err(_) => return false,
+        let job = {
+            let rx = job_rx.lock().unwrap();
+            rx.recv_timeout(Duration::from_millis(50))
         };
-        match &self.config.size_in_bytes {
-            None => true,
-            Some(SizeType::Gte(s)) => size >= *s,
-            Some(SizeType::Eq(s)) => size == *s,
-            Some(SizeType::Le(s)) => size <= *s,
+
+        match job {
+            Ok(job) => {
+                process_job(&find, &job, &shared);
+                if shared.pending.fetch_sub(1, Ordering::AcqRel) == 1 {
+                    shared.done.store(true, Ordering::Release);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return,
         }
     }
+}
 
-
-    fn find_type_is_dir(&self) -> bool {
-        self.config.find_type == FindType::Dir
+/// Renders a matched path for display, using the configured path separator
+/// in place of the OS one and appending a trailing separator to
+/// directories, so output is unambiguous (e.g. for piping into `xargs -0`).
+fn format_for_display(find: &Find, path: &Path, is_dir: bool) -> String {
+    let separator = &find.config.path_separator;
+    let mut rendered = path.display().to_string().replace(std::path::MAIN_SEPARATOR, separator);
+    if is_dir && !rendered.ends_with(separator.as_str()) {
+        rendered.push_str(separator);
     }
+    rendered
+}
+
+/// Prints a match, runs `--exec` for it, or stashes it for `--exec-batch`,
+/// depending on what the user asked for.
+fn handle_match(find: &Find, shared: &Shared, path: &Path, is_dir: bool) {
+    let owned;
+    let path = if find.config.absolute_path {
+        owned = make_absolute(path);
+        owned.as_path()
+    } else {
+        path
+    };
+
+    let rendered = format_for_display(find, path, is_dir);
 
-    fn file_matches(&self, path: &Path) -> bool {
-        self.config.find_type == FindType::File
-            && self.match_name(&path)
-            && self.match_size(&path)
+    match &find.config.exec {
+        Some(ExecMode::PerMatch(template)) => {
+            let code = exec::run_per_match(template, path, &rendered);
+            if code != 0 {
+                let _ = shared.exec_failed.compare_exchange(
+                    0,
+                    code,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                );
+            }
+        }
+        Some(ExecMode::Batch(_)) => {
+            shared.batch_matches.lock().unwrap().push(rendered);
+        }
+        None => {
+            let mut out = shared.writer.lock().unwrap();
+            let _ = writeln!(out, "{}", rendered);
+        }
     }
+}
 
-    fn symlink_matches(&self, path: &Path) -> bool {
-        self.config.find_type == FindType::Symlink
-            && self.match_name(&path)
+/// Whether this directory has already been descended into, so `--follow`
+/// doesn't loop forever on a symlink cycle. When `--follow` is set, records
+/// the directory as visited as a side effect; otherwise always `false`.
+fn already_visited(find: &Find, shared: &Shared, entry: &DirEntry) -> bool {
+    if !find.config.follow {
+        return false;
+    }
+    match entry.metadata() {
+        Some(meta) => !shared.visited.lock().unwrap().insert((meta.dev(), meta.ino())),
+        None => false,
     }
+}
 
-    pub fn run(&mut self, path: &Path) {
-        match get_type(&path) {
-            PathType::File if self.file_matches(path) => println!("{}", path.display()),
-            PathType::Symlink if self.symlink_matches(path) => println!("{}", path.display()),
-            PathType::Directory => {
-                if self.find_type_is_dir() {
-                    println!("{}", path.display());
-                }
-                self.inspect_dir(path);
+fn process_job(find: &Find, job: &Job, shared: &Shared) {
+    let entry = DirEntry::new(&job.path, find.config.follow);
+    match entry.path_type() {
+        PathType::Directory => {
+            if find.matches(&entry) {
+                handle_match(find, shared, &job.path, true);
+            }
+            if !already_visited(find, shared, &entry) {
+                inspect_dir(find, job, shared);
+            }
+        }
+        PathType::File | PathType::Symlink => {
+            if find.matches(&entry) {
+                handle_match(find, shared, &job.path, false);
             }
-            _ => {}
         }
+        PathType::Unknown => {}
+    }
+}
+
+/// Whether `entry` is a directory, consulting the symlink's target instead
+/// of the symlink itself when `--follow` is set, so a dir-only ignore rule
+/// (`build/`) prunes a symlinked directory the same way `--follow` already
+/// treats it as a directory everywhere else in the traversal.
+fn entry_is_dir(entry: &fs::DirEntry, follow: bool) -> bool {
+    if follow {
+        fs::metadata(entry.path()).map(|m| m.is_dir()).unwrap_or(false)
+    } else {
+        entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false)
     }
+}
 
-    fn inspect_dir(&mut self, path: &Path) {
-        if let Some(depth) = self.config.depth && self.depth == depth {
-            return;
-        }
+fn inspect_dir(find: &Find, job: &Job, shared: &Shared) {
+    if let Some(max_depth) = find.config.depth
+        && job.depth == max_depth
+    {
+        return;
+    }
+
+    let ignore_stack = if find.config.no_ignore {
+        job.ignore_stack.clone()
+    } else {
+        job.ignore_stack.push(&job.path)
+    };
+
+    match fs::read_dir(&job.path) {
+        Err(e) => print_error!("{}", e),
+        Ok(entries) => {
+            for entry in entries {
+                match entry {
+                    Ok(entry) => {
+                        if !find.config.hidden && is_hidden(&entry.file_name()) {
+                            continue;
+                        }
 
-        self.depth += 1;
+                        let is_dir = entry_is_dir(&entry, find.config.follow);
+                        if ignore_stack.is_ignored(&entry.path(), is_dir) {
+                            continue;
+                        }
 
-        match fs::read_dir(path) {
-            Err(e) => print_error!("{}", e),
-            Ok(entries) => {
-                for entry in entries {
-                    if let Ok(entry) = entry {
-                        self.run(&entry.path());
-                    } else {
-                        print_error!("{}: ", entry.err().unwrap());
+                        let child = Job {
+                            path: entry.path(),
+                            depth: job.depth + 1,
+                            ignore_stack: ignore_stack.clone(),
+                        };
+                        if !is_dir {
+                            process_job(find, &child, shared);
+                            continue;
+                        }
+                        // Register the job before handing it off so the
+                        // pending count never dips to zero while work is
+                        // still in flight between threads.
+                        shared.pending.fetch_add(1, Ordering::AcqRel);
+                        if shared.job_tx.send(child).is_err() {
+                            shared.pending.fetch_sub(1, Ordering::AcqRel);
+                        }
                     }
+                    Err(e) => print_error!("{}: ", e),
                 }
             }
         }
-
-        self.depth -= 1;
     }
 }
 
@@ -132,41 +348,58 @@ fn main() {
     }
 
     let root_path = config.dir.clone();
-    let mut find = Find::new(config);
+    let find = Find::new(config);
 
-    find.run(&root_path);
+    find.run(root_path);
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::config::{Config, NameMatcher};
+    use crate::filter::DirEntry;
+    use crate::{Find, PathType};
+    use regex::Regex;
     use std::fs;
     use std::os::unix;
-    use crate::{get_type, Find, PathType};
     use std::path::PathBuf;
-    use regex::Regex;
-    use crate::config::Config;
 
     #[test]
     fn test_match_name() {
         let mut config = Config::default();
-        config.regex = Some(Regex::new("^(.*).rs$").unwrap());
+        config.name_matcher = Some(NameMatcher::Regex(Regex::new("^(.*).rs$").unwrap()));
         let find = Find::new(config);
 
-        assert_eq!(find.match_name(&PathBuf::from("./src/main.rs")), true);
-        assert_eq!(find.match_name(&PathBuf::from("./src/config.rs")), true);
+        assert_eq!(find.matches(&DirEntry::new(&PathBuf::from("./src/main.rs"), false)), true);
+        assert_eq!(find.matches(&DirEntry::new(&PathBuf::from("./src/config.rs"), false)), true);
     }
 
     #[test]
     fn test_get_type() {
-        assert_eq!(get_type(&PathBuf::from("/foo")), PathType::Unknown);
-        assert_eq!(get_type(&PathBuf::from("./src/main.rs")), PathType::File);
-        assert_eq!(get_type(&PathBuf::from("./src")), PathType::Directory);
+        assert_eq!(DirEntry::new(&PathBuf::from("/foo"), false).path_type(), PathType::Unknown);
+        assert_eq!(DirEntry::new(&PathBuf::from("./src/main.rs"), false).path_type(), PathType::File);
+        assert_eq!(DirEntry::new(&PathBuf::from("./src"), false).path_type(), PathType::Directory);
 
-        unix::fs::symlink(&PathBuf::from("./src/main.rs"),
-                          &PathBuf::from("./main.rs.sl")).unwrap();
+        unix::fs::symlink(&PathBuf::from("./src/main.rs"), &PathBuf::from("./main.rs.sl"))
+            .unwrap();
 
-        assert_eq!(get_type(&PathBuf::from("./main.rs.sl")), PathType::Symlink);
+        assert_eq!(
+            DirEntry::new(&PathBuf::from("./main.rs.sl"), false).path_type(),
+            PathType::Symlink
+        );
 
         fs::remove_file(&PathBuf::from("./main.rs.sl")).unwrap()
     }
+
+    #[test]
+    fn test_entry_is_dir_follows_symlinks_only_when_asked() {
+        let dir = std::env::temp_dir().join("rfind-entry-is-dir-test");
+        fs::create_dir_all(&dir).unwrap();
+        unix::fs::symlink(&dir, dir.join("self_link")).unwrap();
+
+        let entry = fs::read_dir(&dir).unwrap().next().unwrap().unwrap();
+        assert_eq!(crate::entry_is_dir(&entry, false), false);
+        assert_eq!(crate::entry_is_dir(&entry, true), true);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }